@@ -0,0 +1,179 @@
+use crate::{BorrowedBuf, BorrowedCursor, Read2};
+use std::cmp;
+use std::io::{self, BufRead, Read};
+use std::mem;
+use std::mem::MaybeUninit;
+
+const DEFAULT_BUF_SIZE: usize = 8 * 1024;
+
+/// A buffered reader built directly on `Read2::read_buf`.
+///
+/// Unlike `std::io::BufReader`, which fills its backing buffer through `Read::read` and so must zero it before
+/// every refill, `BufReader2` carries its `initialized` watermark forward across refills. This means the buffer is
+/// zeroed at most once over the reader's entire lifetime, rather than once per refill.
+pub struct BufReader2<R> {
+    inner: R,
+    buf: Box<[MaybeUninit<u8>]>,
+    pos: usize,
+    filled: usize,
+    initialized: usize,
+}
+
+impl<R: Read2> BufReader2<R> {
+    /// Creates a new `BufReader2` with a default buffer capacity.
+    pub fn new(inner: R) -> BufReader2<R> {
+        BufReader2::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Creates a new `BufReader2` with the specified buffer capacity.
+    pub fn with_capacity(capacity: usize, inner: R) -> BufReader2<R> {
+        BufReader2 {
+            inner,
+            buf: vec![MaybeUninit::uninit(); capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            initialized: 0,
+        }
+    }
+
+    /// Returns a reference to the wrapped reader.
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped reader.
+    ///
+    /// It is inadvisable to read directly from the underlying reader, as doing so may discard data already
+    /// buffered here.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `BufReader2`, returning the underlying reader.
+    ///
+    /// Any buffered data is discarded.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill_buf_slow(&mut self) -> io::Result<()> {
+        let mut buf = BorrowedBuf::new_uninit(&mut self.buf);
+        unsafe {
+            buf.set_init(self.initialized);
+        }
+
+        self.inner.read_buf(buf.unfilled())?;
+
+        self.pos = 0;
+        self.filled = buf.filled();
+        self.initialized = buf.initialized();
+
+        Ok(())
+    }
+}
+
+impl<R: Read2> Read for BufReader2<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Bypass the internal buffer entirely for reads at least as large as it when it's currently empty, the same
+        // optimization `std::io::BufReader` makes.
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            return self.inner.read(buf);
+        }
+
+        let available = self.fill_buf()?;
+        let n = cmp::min(available.len(), buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read2> BufRead for BufReader2<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.filled {
+            self.fill_buf_slow()?;
+        }
+
+        Ok(unsafe { cast_init(&self.buf[self.pos..self.filled]) })
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = cmp::min(self.pos + amt, self.filled);
+    }
+}
+
+impl<R: Read2> Read2 for BufReader2<R> {
+    fn read_buf(&mut self, mut buf: BorrowedCursor<'_>) -> io::Result<()> {
+        if buf.capacity() == 0 {
+            return Ok(());
+        }
+
+        // Bypass the internal buffer for reads at least as large as it when it's currently empty, avoiding an extra
+        // copy.
+        if self.pos == self.filled && buf.capacity() >= self.buf.len() {
+            return self.inner.read_buf(buf);
+        }
+
+        let available = self.fill_buf()?;
+        let n = cmp::min(available.len(), buf.capacity());
+        buf.append(&available[..n]);
+        self.consume(n);
+        Ok(())
+    }
+}
+
+#[inline]
+unsafe fn cast_init(buf: &[MaybeUninit<u8>]) -> &[u8] {
+    mem::transmute(buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    // `Read2`'s default `read_buf` delegates to `Read`, so wrapping a `Cursor` is enough to exercise the buffering
+    // logic without needing a real file descriptor.
+    struct Inner(Cursor<Vec<u8>>);
+
+    impl Read for Inner {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Read2 for Inner {}
+
+    #[test]
+    fn read_smaller_than_buffer() {
+        let data = (0..100).collect::<Vec<u8>>();
+        let mut reader = BufReader2::with_capacity(16, Inner(Cursor::new(data.clone())));
+
+        let mut out = vec![];
+        let mut chunk = [0; 5];
+        loop {
+            let n = reader.read(&mut chunk).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&chunk[..n]);
+        }
+
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn read_larger_than_buffer_bypasses() {
+        let data = (0..100).collect::<Vec<u8>>();
+        let mut reader = BufReader2::with_capacity(16, Inner(Cursor::new(data.clone())));
+
+        let mut out = [0; 100];
+        let n = reader.read(&mut out).unwrap();
+
+        assert_eq!(n, 100);
+        assert_eq!(&out[..], &data[..]);
+    }
+}
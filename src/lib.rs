@@ -1,9 +1,15 @@
+pub use crate::buf_reader::*;
 pub use crate::read::*;
 pub use crate::read_buf::*;
 pub use crate::read_bufs::*;
+pub use crate::size_hint::*;
+pub use crate::uninit_slice::*;
 pub use crate::vec::*;
 
+mod buf_reader;
 mod read;
 mod read_buf;
 mod read_bufs;
+mod size_hint;
+mod uninit_slice;
 mod vec;
@@ -1,18 +1,31 @@
-use crate::{ReadBuf, ReadBufs, VecExt};
-use std::io::{self, Read, Write};
+use crate::{BorrowedBuf, BorrowedCursor, MaybeUninitIoSliceMut, ReadBufs, SizeHint, VecExt};
+use std::cmp;
+use std::fs::File;
+use std::io::{self, IoSlice, Read, Write};
+use std::mem;
 use std::mem::MaybeUninit;
 use std::net::TcpStream;
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// The minimum amount by which `read_to_end2` grows its buffer when no size hint is available.
+const MIN_GROWTH: usize = 32;
+
+/// The maximum amount by which `read_to_end2` grows its buffer in one step.
+const MAX_GROWTH: usize = 1024 * 1024;
 
 pub trait Read2: Read {
-    /// Pull some bytes from this source into the specified buffer, returning how many bytes were read.
+    /// Pull some bytes from this source into the specified cursor.
     ///
-    /// This is equivalent to the `read` method, except that it is passed a `ReadBuf` rather than `[u8]` to allow use
-    /// with uninitialized buffers.
+    /// This is equivalent to the `read` method, except that it is passed a `BorrowedCursor` rather than `[u8]` to
+    /// allow use with uninitialized buffers. The number of bytes read can be recovered from `buf.written()`.
     ///
     /// The default implementation delegates to `read`.
-    fn read_buf(&mut self, buf: &mut ReadBuf) -> io::Result<usize> {
-        self.read(buf.as_init())
+    fn read_buf(&mut self, mut buf: BorrowedCursor<'_>) -> io::Result<()> {
+        let n = self.read(buf.init_mut())?;
+        unsafe {
+            buf.advance(n);
+        }
+        Ok(())
     }
 
     /// Like `read_buf`, except that it reads into a slice of buffers.
@@ -25,32 +38,27 @@ pub trait Read2: Read {
         self.read_vectored(bufs.as_init())
     }
 
+    /// Determines if this source has an efficient `read_bufs` implementation.
+    ///
+    /// This mirrors `Read::is_read_vectored`, and exists for the same reason: so that callers like `copy` can pick
+    /// a single-buffer or vectored strategy without guessing.
+    ///
+    /// The default implementation returns `false`.
+    fn is_read_vectored(&self) -> bool {
+        false
+    }
+
     /// Read the exact number of bytes required to fill `buf`.
     ///
-    /// This is equivalent to the `read_exact` method, except that it is passed a `ReadBuf` rather than `[u8]` to allow
-    /// use with uninitialized buffers.
-    fn read_buf_exact(&mut self, buf: &mut ReadBuf) -> io::Result<()> {
-        let mut base = 0;
-        while buf.len() > base {
-            let mut temp_buf = unsafe {
-                let temp_init = buf
-                    .initialized()
-                    .checked_sub(base)
-                    .expect("invalid initialized state");
-                let mut temp_buf = ReadBuf::new_uninit(&mut buf.as_uninit()[base..]);
-                temp_buf.assume_initialized(temp_init);
-                temp_buf
-            };
-            let len = self.read_buf(&mut temp_buf)?;
-            if len == 0 {
+    /// This is equivalent to the `read_exact` method, except that it is passed a `BorrowedCursor` rather than
+    /// `[u8]` to allow use with uninitialized buffers.
+    fn read_buf_exact(&mut self, mut buf: BorrowedCursor<'_>) -> io::Result<()> {
+        while buf.capacity() > 0 {
+            let written = buf.written();
+            self.read_buf(buf.reborrow())?;
+            if buf.written() == written {
                 return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
             }
-
-            let new_initialized = base + temp_buf.initialized();
-            unsafe {
-                buf.assume_initialized(new_initialized);
-            }
-            base += len;
         }
 
         Ok(())
@@ -59,22 +67,34 @@ pub trait Read2: Read {
     /// Read all bytes until EOF in this source, placing them into `buf`.
     ///
     /// This is equivalent to `read_to_end`, except that it uses `read_buf` rather than `read`, allowing it to avoid
-    /// initializing components of `buf` before filling them.
-    fn read_to_end2(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+    /// initializing components of `buf` before filling them. It also consults `SizeHint` to reserve space up front
+    /// rather than growing the buffer a little at a time.
+    fn read_to_end2(&mut self, buf: &mut Vec<u8>) -> io::Result<usize>
+    where
+        Self: SizeHint,
+    {
         let initial_len = buf.len();
 
+        let lower = usize::try_from(self.lower()).unwrap_or(usize::MAX);
+        if lower > buf.capacity() - buf.len() {
+            buf.reserve(lower);
+        }
+
         let mut initialized = 0;
         loop {
             if buf.len() == buf.capacity() {
-                buf.reserve(32);
+                let growth = buf.capacity().clamp(MIN_GROWTH, MAX_GROWTH);
+                buf.reserve(growth);
             }
 
-            let mut read_buf = ReadBuf::new_uninit(buf.spare_capacity_mut());
+            let mut read_buf = BorrowedBuf::new_uninit(buf.spare_capacity_mut());
             unsafe {
-                read_buf.assume_initialized(initialized);
+                read_buf.set_init(initialized);
             }
 
-            let nread = self.read_buf(&mut read_buf)?;
+            self.read_buf(read_buf.unfilled())?;
+
+            let nread = read_buf.filled();
             if nread == 0 {
                 return Ok(buf.len() - initial_len);
             }
@@ -91,56 +111,380 @@ pub trait Read2: Read {
     }
 }
 
-impl Read2 for TcpStream {
-    fn read_buf(&mut self, buf: &mut ReadBuf) -> io::Result<usize> {
-        unsafe {
-            let raw_buf = buf.as_uninit();
-            let ret = libc::read(self.as_raw_fd(), raw_buf.as_mut_ptr().cast(), raw_buf.len());
-            if ret < 0 {
-                Err(io::Error::last_os_error())
-            } else {
-                let len = ret as usize;
-                buf.assume_initialized(len);
-                Ok(len)
-            }
+/// Reads from a raw file descriptor via `libc::read`, committing the result to `buf`.
+fn read_buf_raw(fd: RawFd, mut buf: BorrowedCursor<'_>) -> io::Result<()> {
+    unsafe {
+        let raw_buf = buf.as_uninit();
+        let ret = libc::read(fd, raw_buf.as_mut_ptr().cast(), raw_buf.len());
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            let len = ret as usize;
+            buf.set_init(len);
+            buf.advance(len);
+            Ok(())
         }
     }
+}
 
-    fn read_bufs(&mut self, bufs: &mut ReadBufs) -> io::Result<usize> {
-        unsafe {
-            let raw_bufs = bufs.as_uninit();
-            let ret = libc::readv(
-                self.as_raw_fd(),
-                raw_bufs.as_mut_ptr().cast(),
-                raw_bufs.len() as i32,
-            );
-            if ret < 0 {
-                Err(io::Error::last_os_error())
-            } else {
-                let len = ret as usize;
-                bufs.assume_initialized(len);
-                Ok(len)
-            }
+/// Reads from a raw file descriptor via `libc::readv`, committing the result to `bufs`.
+fn read_bufs_raw(fd: RawFd, bufs: &mut ReadBufs) -> io::Result<usize> {
+    unsafe {
+        let raw_bufs = bufs.as_uninit();
+        let ret = libc::readv(fd, raw_bufs.as_mut_ptr().cast(), raw_bufs.len() as i32);
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            let len = ret as usize;
+            bufs.assume_initialized(len);
+            Ok(len)
         }
     }
 }
 
+impl Read2 for TcpStream {
+    fn read_buf(&mut self, buf: BorrowedCursor<'_>) -> io::Result<()> {
+        read_buf_raw(self.as_raw_fd(), buf)
+    }
+
+    fn read_bufs(&mut self, bufs: &mut ReadBufs) -> io::Result<usize> {
+        read_bufs_raw(self.as_raw_fd(), bufs)
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+}
+
+impl Read2 for File {
+    fn read_buf(&mut self, buf: BorrowedCursor<'_>) -> io::Result<()> {
+        read_buf_raw(self.as_raw_fd(), buf)
+    }
+
+    fn read_bufs(&mut self, bufs: &mut ReadBufs) -> io::Result<usize> {
+        read_bufs_raw(self.as_raw_fd(), bufs)
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+}
+
+impl Read2 for &File {
+    fn read_buf(&mut self, buf: BorrowedCursor<'_>) -> io::Result<()> {
+        read_buf_raw(self.as_raw_fd(), buf)
+    }
+
+    fn read_bufs(&mut self, bufs: &mut ReadBufs) -> io::Result<usize> {
+        read_bufs_raw(self.as_raw_fd(), bufs)
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+}
+
+/// The number of chunks `copy` uses for its vectored transfer buffer.
+const VECTORED_CHUNKS: usize = 8;
+
+/// The size in bytes of each chunk of `copy`'s vectored transfer buffer.
+const VECTORED_CHUNK_SIZE: usize = 512;
+
 /// A reimplementation of `io::copy`, except that it uses `read_buf` to avoid initializing the stack buffer.
+///
+/// When `reader` supports vectored I/O (as reported by `is_read_vectored`), the transfer is instead driven through
+/// `read_bufs` and `write_vectored` over `VECTORED_CHUNKS` stack chunks of `VECTORED_CHUNK_SIZE` bytes each.
+/// `Write::is_write_vectored` isn't something we can consult here - it's nightly-only - so we don't gate on whether
+/// the writer specially supports vectored writes; `write_vectored` always has a correct, if unspecialized, fallback
+/// for writers that don't.
 pub fn copy<R, W>(reader: &mut R, writer: &mut W) -> io::Result<u64>
 where
     R: Read2,
     W: Write,
 {
+    if reader.is_read_vectored() {
+        return copy_vectored(reader, writer);
+    }
+
     let mut buf = [MaybeUninit::uninit(); 4096];
-    let mut buf = ReadBuf::new_uninit(&mut buf);
+    let mut buf = BorrowedBuf::new_uninit(&mut buf);
+    let mut len = 0;
+
+    loop {
+        reader.read_buf(buf.unfilled())?;
+        let nread = buf.filled();
+        if nread == 0 {
+            return Ok(len);
+        }
+        len += nread as u64;
+        writer.write_all(buf.filled_slice())?;
+        buf.clear();
+    }
+}
+
+fn copy_vectored<R, W>(reader: &mut R, writer: &mut W) -> io::Result<u64>
+where
+    R: Read2,
+    W: Write,
+{
+    let mut storage = [[MaybeUninit::uninit(); VECTORED_CHUNK_SIZE]; VECTORED_CHUNKS];
+    let mut chunks = storage.each_mut().map(|chunk| MaybeUninitIoSliceMut::new(chunk));
+    let mut bufs = ReadBufs::new_uninit(&mut chunks);
     let mut len = 0;
 
     loop {
-        let nread = reader.read_buf(&mut buf)?;
+        let nread = reader.read_bufs(&mut bufs)?;
         if nread == 0 {
             return Ok(len);
         }
         len += nread as u64;
-        writer.write_all(&buf.as_slices().0[..nread])?;
+
+        let mut iovecs: [IoSlice; VECTORED_CHUNKS] = std::array::from_fn(|_| IoSlice::new(&[]));
+        let mut count = 0;
+        let mut remaining = nread;
+        for chunk in unsafe { bufs.as_uninit() } {
+            if remaining == 0 {
+                break;
+            }
+
+            let take = cmp::min(remaining, chunk.len());
+            // SAFETY: `read_bufs` only reports `nread` bytes read when the source has actually written that many
+            // bytes into the beginning of these chunks, in order.
+            iovecs[count] = IoSlice::new(unsafe { cast_init(&chunk[..take]) });
+            count += 1;
+            remaining -= take;
+        }
+
+        write_all_vectored(writer, &iovecs[..count])?;
+    }
+}
+
+/// Writes the entirety of `bufs` to `writer`, retrying as needed to work around partial vectored writes.
+///
+/// `Write::write_all_vectored` isn't stable, so this reimplements it in terms of the stable `write_vectored`.
+fn write_all_vectored<W: Write>(writer: &mut W, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+    let mut skip = 0;
+
+    loop {
+        let mut remaining: [IoSlice; VECTORED_CHUNKS] = std::array::from_fn(|_| IoSlice::new(&[]));
+        let mut count = 0;
+        let mut to_skip = skip;
+
+        for buf in bufs {
+            if to_skip >= buf.len() {
+                to_skip -= buf.len();
+                continue;
+            }
+
+            remaining[count] = IoSlice::new(&buf[to_skip..]);
+            to_skip = 0;
+            count += 1;
+        }
+
+        if count == 0 {
+            return Ok(());
+        }
+
+        let n = writer.write_vectored(&remaining[..count])?;
+        if n == 0 {
+            return Err(io::Error::from(io::ErrorKind::WriteZero));
+        }
+        skip += n;
+    }
+}
+
+#[inline]
+unsafe fn cast_init(buf: &[MaybeUninit<u8>]) -> &[u8] {
+    mem::transmute(buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    // A reader that only ever hands back `chunk` bytes per call, regardless of how much room the caller offers.
+    // `Read2`'s default `read_buf` delegates to `Read`, so this is enough to force callers like `read_buf_exact`
+    // through multiple round trips instead of satisfying the whole request in one shot.
+    struct ChunkedReader {
+        inner: Cursor<Vec<u8>>,
+        chunk: usize,
+    }
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = cmp::min(self.chunk, buf.len());
+            self.inner.read(&mut buf[..n])
+        }
+    }
+
+    impl Read2 for ChunkedReader {}
+
+    impl SizeHint for ChunkedReader {}
+
+    #[test]
+    fn read_buf_exact_multiple_chunks() {
+        let data = (0..100).collect::<Vec<u8>>();
+        let mut reader = ChunkedReader {
+            inner: Cursor::new(data.clone()),
+            chunk: 3,
+        };
+
+        let mut out = [MaybeUninit::uninit(); 10];
+        let mut out = BorrowedBuf::new_uninit(&mut out);
+        reader.read_buf_exact(out.unfilled()).unwrap();
+
+        assert_eq!(out.filled_slice(), &data[..10]);
+    }
+
+    #[test]
+    fn read_buf_exact_eof() {
+        let mut reader = ChunkedReader {
+            inner: Cursor::new(vec![0; 5]),
+            chunk: 3,
+        };
+
+        let mut out = [MaybeUninit::uninit(); 10];
+        let mut out = BorrowedBuf::new_uninit(&mut out);
+        let err = reader.read_buf_exact(out.unfilled()).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_to_end2_multiple_chunks() {
+        let data = (0..200).collect::<Vec<u8>>();
+        let mut reader = ChunkedReader {
+            inner: Cursor::new(data.clone()),
+            chunk: 7,
+        };
+
+        let mut out = vec![];
+        let n = reader.read_to_end2(&mut out).unwrap();
+
+        assert_eq!(n, data.len());
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn copy_scalar() {
+        let data = (0..10_000).map(|i| i as u8).collect::<Vec<u8>>();
+        let mut reader = ChunkedReader {
+            inner: Cursor::new(data.clone()),
+            chunk: 13,
+        };
+
+        let mut out = vec![];
+        let n = copy(&mut reader, &mut out).unwrap();
+
+        assert_eq!(n, data.len() as u64);
+        assert_eq!(out, data);
+    }
+
+    // A reader that reports `is_read_vectored() == true` and hands back `step` bytes per `read_bufs` call,
+    // deliberately not aligned to `VECTORED_CHUNK_SIZE`, to drive `copy_vectored`'s `remaining`/`take` iovec
+    // slicing across chunk boundaries.
+    struct VectoredReader {
+        data: Vec<u8>,
+        pos: usize,
+        step: usize,
+    }
+
+    impl Read for VectoredReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            unreachable!("copy_vectored should never fall back to the scalar read path");
+        }
+    }
+
+    impl Read2 for VectoredReader {
+        fn is_read_vectored(&self) -> bool {
+            true
+        }
+
+        fn read_bufs(&mut self, bufs: &mut ReadBufs) -> io::Result<usize> {
+            let remaining = &self.data[self.pos..];
+            let n = cmp::min(self.step, remaining.len());
+
+            let mut written = 0;
+            for chunk in unsafe { bufs.as_uninit() } {
+                if written == n {
+                    break;
+                }
+
+                let take = cmp::min(n - written, chunk.len());
+                for (slot, &byte) in chunk[..take].iter_mut().zip(&remaining[written..][..take]) {
+                    *slot = MaybeUninit::new(byte);
+                }
+                written += take;
+            }
+
+            unsafe {
+                bufs.assume_initialized(written);
+            }
+            self.pos += written;
+            Ok(written)
+        }
+    }
+
+    #[test]
+    fn copy_vectored_irregular_chunks() {
+        let data = (0..10_000).map(|i| i as u8).collect::<Vec<u8>>();
+        let mut reader = VectoredReader {
+            data: data.clone(),
+            pos: 0,
+            step: 700,
+        };
+
+        let mut out = vec![];
+        let n = copy(&mut reader, &mut out).unwrap();
+
+        assert_eq!(n, data.len() as u64);
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("read-buf-read-test-{:?}", std::thread::current().id()));
+
+        let data = (0..5_000).map(|i| i as u8).collect::<Vec<u8>>();
+        std::fs::write(&path, &data).unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        let mut out = vec![];
+        let n = file.read_to_end2(&mut out).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(n, data.len());
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn tcp_stream_round_trip() {
+        use std::net::TcpListener;
+
+        let data = (0..20_000).map(|i| i as u8).collect::<Vec<u8>>();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server_data = data.clone();
+        let server = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            // A partial, not-page-aligned write exercises `copy_vectored` across multiple `read_bufs` calls.
+            for chunk in server_data.chunks(777) {
+                socket.write_all(chunk).unwrap();
+            }
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        let mut out = vec![];
+        let n = copy(&mut client, &mut out).unwrap();
+
+        server.join().unwrap();
+
+        assert_eq!(n, data.len() as u64);
+        assert_eq!(out, data);
     }
 }
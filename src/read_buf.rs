@@ -1,155 +1,300 @@
+use crate::UninitSlice;
 use std::mem;
 use std::mem::MaybeUninit;
 
-/// A wrapper over a slice of incrementally-initialized bytes.
-pub struct ReadBuf<'a> {
-    buf: &'a mut [MaybeUninit<u8>],
-    initialized: usize,
+/// A wrapper over an incrementally-initialized and incrementally-filled slice of bytes.
+///
+/// Unlike a single `initialized` watermark, `BorrowedBuf` tracks two independent, monotonically increasing offsets:
+/// `filled`, the number of bytes a reader has actually produced, and `initialized`, the number of bytes known to be
+/// free of uninitialized memory. The invariant `filled <= initialized <= capacity` always holds, and bytes may only
+/// be written through a [`BorrowedCursor`] obtained via [`unfilled`](BorrowedBuf::unfilled), which cannot be used to
+/// shrink either offset.
+pub struct BorrowedBuf<'data> {
+    buf: &'data mut [MaybeUninit<u8>],
+    filled: usize,
+    init: usize,
 }
 
-impl<'a> ReadBuf<'a> {
-    /// Creates a new `ReadBuf` from a fully initialized slice.
+impl<'data> BorrowedBuf<'data> {
+    /// Creates a new `BorrowedBuf` from a fully initialized and filled slice.
     #[inline]
-    pub fn new(buf: &'a mut [u8]) -> ReadBuf<'a> {
-        ReadBuf {
-            initialized: buf.len(),
+    pub fn new(buf: &'data mut [u8]) -> BorrowedBuf<'data> {
+        BorrowedBuf {
+            filled: buf.len(),
+            init: buf.len(),
             buf: unsafe { mem::transmute(buf) },
         }
     }
 
-    /// Creates a new `ReadBuf` from a fully uninitialized slice.
-    ///
-    /// Use `assume_initialized` if part of the slice is known to be already initialized.
+    /// Creates a new `BorrowedBuf` from a fully uninitialized and unfilled slice.
     #[inline]
-    pub fn new_uninit(buf: &'a mut [MaybeUninit<u8>]) -> ReadBuf<'a> {
-        ReadBuf {
+    pub fn new_uninit(buf: &'data mut [MaybeUninit<u8>]) -> BorrowedBuf<'data> {
+        BorrowedBuf {
             buf,
-            initialized: 0,
+            filled: 0,
+            init: 0,
         }
     }
 
-    /// Returns the size of the slice.
+    /// Returns the total size of the buffer.
     #[inline]
-    pub fn len(&self) -> usize {
+    pub fn capacity(&self) -> usize {
         self.buf.len()
     }
 
-    /// Returns the number of bytes at the beginning of the slice that are known to be initialized.
+    /// Returns the number of bytes at the beginning of the buffer that have been filled in.
+    #[inline]
+    pub fn filled(&self) -> usize {
+        self.filled
+    }
+
+    /// Returns the number of bytes at the beginning of the buffer that are known to be initialized.
     #[inline]
     pub fn initialized(&self) -> usize {
-        self.initialized
+        self.init
     }
 
-    /// Asserts that the first `n` bytes at the beginning of the slice are initialized.
+    /// Returns a shared reference to the filled portion of the buffer.
+    #[inline]
+    pub fn filled_slice(&self) -> &[u8] {
+        unsafe { cast_init(&self.buf[..self.filled]) }
+    }
+
+    /// Asserts that the first `n` bytes of the buffer are initialized.
     ///
-    /// `ReadBuf` assumes that bytes are never "de-initialized", so this method does nothing when called with fewer
-    /// bytes than are already known to be initialized.
+    /// `BorrowedBuf` assumes that bytes are never "de-initialized", so this method does nothing when called with
+    /// fewer bytes than are already known to be initialized.
     ///
     /// # Safety
     ///
-    /// The caller must have already initialized the first `n` bytes of the slice.
+    /// The caller must have already initialized the first `n` bytes of the buffer.
+    #[inline]
+    pub unsafe fn set_init(&mut self, n: usize) {
+        self.init = usize::max(self.init, n);
+    }
+
+    /// Unfills the buffer, resetting it back to empty.
+    ///
+    /// The `initialized` watermark is left untouched, so bytes that were previously zeroed do not need to be
+    /// zeroed again the next time the buffer is filled.
+    #[inline]
+    pub fn clear(&mut self) -> &mut Self {
+        self.filled = 0;
+        self
+    }
+
+    /// Returns a cursor over the unfilled portion of the buffer.
+    #[inline]
+    pub fn unfilled<'this>(&'this mut self) -> BorrowedCursor<'this> {
+        BorrowedCursor {
+            start: self.filled,
+            // SAFETY: `BorrowedCursor` only ever reaches the buffer through this reference, and it cannot outlive
+            // the borrow of `self` that produced it, so lengthening the lifetime here is sound.
+            buf: unsafe {
+                mem::transmute::<&'this mut BorrowedBuf<'data>, &'this mut BorrowedBuf<'this>>(self)
+            },
+        }
+    }
+}
+
+/// A writable view of the unfilled tail of a [`BorrowedBuf`].
+///
+/// A cursor can only move the underlying buffer's `filled` and `initialized` offsets forward, so a reader cannot
+/// accidentally de-initialize memory or under-report how many bytes it actually produced.
+pub struct BorrowedCursor<'a> {
+    buf: &'a mut BorrowedBuf<'a>,
+    start: usize,
+}
+
+impl<'a> BorrowedCursor<'a> {
+    /// Returns a cursor over the same unfilled tail with a shorter lifetime.
+    #[inline]
+    pub fn reborrow<'this>(&'this mut self) -> BorrowedCursor<'this> {
+        BorrowedCursor {
+            start: self.start,
+            // SAFETY: the reborrowed cursor cannot outlive `self`, and `self` remains borrowed for its lifetime, so
+            // no two cursors can access the buffer at once.
+            buf: unsafe {
+                mem::transmute::<&'this mut BorrowedBuf<'a>, &'this mut BorrowedBuf<'this>>(
+                    self.buf,
+                )
+            },
+        }
+    }
+
+    /// Returns the number of bytes remaining in the cursor.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity() - self.buf.filled
+    }
+
+    /// Returns the number of bytes that have been written into the cursor so far.
+    #[inline]
+    pub fn written(&self) -> usize {
+        self.buf.filled - self.start
+    }
+
+    /// Zero-initializes the uninitialized portion of the cursor.
+    ///
+    /// Since the underlying buffer's `initialized` offset only ever moves forward, this may be expensive the first
+    /// time it is called for a given region but is "free" after that.
     #[inline]
-    pub unsafe fn assume_initialized(&mut self, n: usize) {
-        self.initialized = usize::max(self.initialized, n);
+    pub fn ensure_init(&mut self) -> &mut Self {
+        let uninit_start = self.buf.init;
+        for byte in &mut self.buf.buf[uninit_start..] {
+            *byte = MaybeUninit::new(0);
+        }
+        self.buf.init = self.buf.buf.len();
+        self
     }
 
-    /// Returns a mutable reference to the entire slice as maybe-uninitialized values.
+    /// Returns a mutable reference to the entire cursor as maybe-uninitialized values.
     ///
     /// # Safety
     ///
     /// The caller must not "de-initialize" bytes that are already known to have been initialized.
     #[inline]
     pub unsafe fn as_uninit(&mut self) -> &mut [MaybeUninit<u8>] {
-        self.buf
+        let filled = self.buf.filled;
+        &mut self.buf.buf[filled..]
     }
 
-    /// Returns mutable references to the initialized and uninitialized portions of the slice.
+    /// Returns a mutable reference to the entire cursor, initializing it as necessary.
     ///
-    /// The two parts are guaranteed to cover the entire range of the inner slice, and be directly contiguous.
+    /// This is equivalent to calling `ensure_init` followed by `as_uninit`, but returns an already-initialized
+    /// slice.
     #[inline]
-    pub fn as_slices(&mut self) -> (&mut [u8], &mut [MaybeUninit<u8>]) {
-        let (head, tail) = self.buf.split_at_mut(self.initialized);
-        (unsafe { cast_init(head) }, tail)
+    pub fn init_mut(&mut self) -> &mut [u8] {
+        self.ensure_init();
+        unsafe { cast_init_mut(self.as_uninit()) }
     }
 
-    /// Returns a mutable reference to the entire slice, initializing it as necessary.
+    /// Returns a safe, write-only view of the cursor.
     ///
-    /// Since `ReadBuf` tracks the initialization state of the slice, this may be expensive the first time it is called
-    /// but is "free" after that.
+    /// Unlike `as_uninit`, this does not require `unsafe`, since `UninitSlice` cannot be used to read back
+    /// uninitialized bytes. Writes through the returned slice must still be followed by `set_init` and `advance` to
+    /// commit them.
     #[inline]
-    pub fn as_init(&mut self) -> &mut [u8] {
-        self.as_init_to(self.buf.len())
+    pub fn uninit_mut(&mut self) -> &mut UninitSlice {
+        UninitSlice::from_uninit(unsafe { self.as_uninit() })
     }
 
-    /// Returns a mutable reference to the first `len` bytes of the slice, initializing it as necessary.
+    /// Asserts that the first `n` bytes of the cursor are initialized.
+    ///
+    /// # Safety
     ///
-    /// Since `ReadBuf` tracks the initialization state of the slice, this may be expensive the first time it is called
-    /// but is "free" after that.
+    /// The caller must have already initialized the first `n` bytes of the cursor.
+    #[inline]
+    pub unsafe fn set_init(&mut self, n: usize) {
+        let filled = self.buf.filled;
+        self.buf.set_init(filled + n);
+    }
+
+    /// Advances the cursor, asserting that `n` bytes have been written to its beginning.
+    ///
+    /// # Safety
+    ///
+    /// The caller must have written `n` bytes to the beginning of the cursor, and those bytes must fall within the
+    /// region already known to be initialized (via a prior call to `ensure_init` or `set_init`).
+    #[inline]
+    pub unsafe fn advance(&mut self, n: usize) -> &mut Self {
+        debug_assert!(
+            self.buf.filled + n <= self.buf.init,
+            "advance past the initialized region"
+        );
+        self.buf.filled += n;
+        self
+    }
+
+    /// Appends a slice of bytes to the cursor, initializing and filling them in one step.
     ///
     /// # Panics
     ///
-    /// Panics if the slice does not have `len` elements.
-    #[inline]
-    pub fn as_init_to(&mut self, len: usize) -> &mut [u8] {
-        if len > self.initialized {
-            for b in &mut self.buf[self.initialized..len] {
-                *b = MaybeUninit::new(0);
-            }
-            self.initialized = len;
+    /// Panics if `buf` does not fit within the cursor's remaining capacity.
+    #[inline]
+    pub fn append(&mut self, buf: &[u8]) {
+        assert!(
+            buf.len() <= self.capacity(),
+            "buf does not fit in the cursor"
+        );
+
+        self.uninit_mut()[..buf.len()].copy_from_slice(buf);
+
+        unsafe {
+            self.set_init(buf.len());
+            self.advance(buf.len());
         }
-        unsafe { cast_init(&mut self.buf[..len]) }
     }
 }
 
 #[inline]
-unsafe fn cast_init(buf: &mut [MaybeUninit<u8>]) -> &mut [u8] {
+unsafe fn cast_init(buf: &[MaybeUninit<u8>]) -> &[u8] {
+    mem::transmute(buf)
+}
+
+#[inline]
+unsafe fn cast_init_mut(buf: &mut [MaybeUninit<u8>]) -> &mut [u8] {
     mem::transmute(buf)
 }
 
 #[cfg(test)]
 mod test {
-    use crate::ReadBuf;
+    use crate::BorrowedBuf;
     use std::mem::MaybeUninit;
 
     #[test]
     fn from_init() {
         let mut buf = [1; 10];
-        let mut buf = ReadBuf::new(&mut buf);
+        let mut buf = BorrowedBuf::new(&mut buf);
 
+        assert_eq!(buf.filled(), 10);
         assert_eq!(buf.initialized(), 10);
+        assert_eq!(buf.filled_slice(), &[1; 10][..]);
 
-        let (head, tail) = buf.as_slices();
-        assert_eq!(head, &[1; 10][..]);
-        assert_eq!(tail.len(), 0);
-
-        let init = buf.as_init();
-        assert_eq!(init, &[1; 10][..]);
+        let cursor = buf.unfilled();
+        assert_eq!(cursor.capacity(), 0);
+        assert_eq!(cursor.written(), 0);
     }
 
     #[test]
     fn from_uninit() {
         let mut buf = [MaybeUninit::new(1); 10];
-        let mut buf = ReadBuf::new_uninit(&mut buf);
+        let mut buf = BorrowedBuf::new_uninit(&mut buf);
 
+        assert_eq!(buf.filled(), 0);
         assert_eq!(buf.initialized(), 0);
 
-        let (head, tail) = buf.as_slices();
-        assert_eq!(head, &mut []);
-        assert_eq!(tail.len(), 10);
-
-        let partial_init = buf.as_init_to(5);
-        assert_eq!(partial_init, &mut [0; 5][..]);
-        partial_init.copy_from_slice(&[2; 5]);
+        let mut cursor = buf.unfilled();
+        assert_eq!(cursor.capacity(), 10);
 
-        assert_eq!(buf.initialized(), 5);
+        cursor.ensure_init();
+        assert_eq!(buf.initialized(), 10);
+        assert_eq!(buf.filled(), 0);
 
-        let (head, tail) = buf.as_slices();
-        assert_eq!(head, &mut [2; 5][..]);
-        assert_eq!(tail.len(), 5);
+        let mut cursor = buf.unfilled();
+        cursor.append(&[2; 5]);
+        assert_eq!(cursor.written(), 5);
 
-        let init = buf.as_init();
-        assert_eq!(init, &mut [2, 2, 2, 2, 2, 0, 0, 0, 0, 0][..]);
+        assert_eq!(buf.filled(), 5);
+        assert_eq!(buf.filled_slice(), &[2; 5][..]);
 
+        buf.clear();
+        assert_eq!(buf.filled(), 0);
         assert_eq!(buf.initialized(), 10);
     }
+
+    #[test]
+    fn cursor_capacity_shrinks_as_written() {
+        let mut buf = [MaybeUninit::uninit(); 10];
+        let mut buf = BorrowedBuf::new_uninit(&mut buf);
+
+        let mut cursor = buf.unfilled();
+        assert_eq!(cursor.capacity(), 10);
+
+        cursor.append(&[1; 4]);
+        assert_eq!(cursor.capacity(), 6);
+
+        cursor.reborrow().append(&[2; 6]);
+        assert_eq!(cursor.capacity(), 0);
+    }
 }
@@ -0,0 +1,73 @@
+use std::fs::File;
+use std::net::TcpStream;
+use std::os::unix::io::AsRawFd;
+
+/// A hint about how many bytes remain to be read from a source.
+///
+/// `Read2` implementors that know how much data they have left, such as `File`, can override this to let
+/// `Read2::read_to_end2` reserve space up front rather than growing its buffer a little at a time.
+pub trait SizeHint {
+    /// Returns a lower bound on the number of bytes remaining to be read.
+    ///
+    /// The default implementation returns `0`, indicating no information is available.
+    fn lower(&self) -> u64 {
+        0
+    }
+
+    /// Returns an upper bound on the number of bytes remaining to be read, if known.
+    ///
+    /// The default implementation returns `None`, indicating no information is available.
+    fn upper(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl SizeHint for TcpStream {}
+
+impl SizeHint for File {
+    fn lower(&self) -> u64 {
+        self.upper().unwrap_or(0)
+    }
+
+    fn upper(&self) -> Option<u64> {
+        let len = self.metadata().ok()?.len();
+
+        // Avoid `Seek::stream_position`, which requires `&mut self`; a size hint should be cheap to compute from a
+        // shared reference.
+        let pos = unsafe { libc::lseek(self.as_raw_fd(), 0, libc::SEEK_CUR) };
+        if pos < 0 {
+            return None;
+        }
+
+        Some(len.saturating_sub(pos as u64))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    #[test]
+    fn file() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("read-buf-size-hint-test-{:?}", std::thread::current().id()));
+
+        let mut file = File::create(&path).unwrap();
+        file.write_all(&[0; 10]).unwrap();
+        drop(file);
+
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(SizeHint::upper(&file), Some(10));
+        assert_eq!(SizeHint::lower(&file), 10);
+
+        file.seek(SeekFrom::Start(4)).unwrap();
+        assert_eq!(SizeHint::upper(&file), Some(6));
+
+        let mut buf = [0; 10];
+        file.read_exact(&mut buf[..6]).unwrap();
+        assert_eq!(SizeHint::upper(&file), Some(0));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use std::mem::MaybeUninit;
+use std::ops::{Index, IndexMut, Range, RangeFrom, RangeFull, RangeTo};
+
+/// A safe, write-only view over a slice of possibly-uninitialized bytes.
+///
+/// Unlike `&mut [MaybeUninit<u8>]`, an `UninitSlice` cannot be read from, only written to, so callers can fill bytes
+/// without needing `unsafe`. This mirrors the role `bytes`' `UninitSlice` plays for `BufMut` implementations: it lets
+/// a single audited type contain the unsafety of writing through a `MaybeUninit` pointer, rather than it being
+/// smeared across every caller that wants to fill a buffer.
+#[repr(transparent)]
+pub struct UninitSlice([MaybeUninit<u8>]);
+
+impl UninitSlice {
+    /// Creates a new `UninitSlice` from a slice of maybe-uninitialized bytes.
+    #[inline]
+    pub fn from_uninit(slice: &mut [MaybeUninit<u8>]) -> &mut UninitSlice {
+        unsafe { &mut *(slice as *mut [MaybeUninit<u8>] as *mut UninitSlice) }
+    }
+
+    /// Returns the number of bytes in the slice.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the slice has a length of 0.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns a raw pointer to the first byte of the slice.
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.0.as_mut_ptr().cast()
+    }
+
+    /// Writes a single byte at the given index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn write_byte(&mut self, index: usize, value: u8) {
+        assert!(index < self.len(), "index out of bounds");
+        unsafe {
+            self.as_mut_ptr().add(index).write(value);
+        }
+    }
+
+    /// Copies the bytes of `src` into the beginning of the slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` is longer than the slice.
+    #[inline]
+    pub fn copy_from_slice(&mut self, src: &[u8]) {
+        assert!(src.len() <= self.len(), "src longer than destination");
+        unsafe {
+            self.as_mut_ptr()
+                .copy_from_nonoverlapping(src.as_ptr(), src.len());
+        }
+    }
+}
+
+impl Index<Range<usize>> for UninitSlice {
+    type Output = UninitSlice;
+
+    #[inline]
+    fn index(&self, index: Range<usize>) -> &UninitSlice {
+        unsafe { &*(&self.0[index] as *const [MaybeUninit<u8>] as *const UninitSlice) }
+    }
+}
+
+impl Index<RangeFrom<usize>> for UninitSlice {
+    type Output = UninitSlice;
+
+    #[inline]
+    fn index(&self, index: RangeFrom<usize>) -> &UninitSlice {
+        &self[index.start..self.len()]
+    }
+}
+
+impl Index<RangeTo<usize>> for UninitSlice {
+    type Output = UninitSlice;
+
+    #[inline]
+    fn index(&self, index: RangeTo<usize>) -> &UninitSlice {
+        &self[0..index.end]
+    }
+}
+
+impl Index<RangeFull> for UninitSlice {
+    type Output = UninitSlice;
+
+    #[inline]
+    fn index(&self, _index: RangeFull) -> &UninitSlice {
+        &self[0..self.len()]
+    }
+}
+
+impl IndexMut<Range<usize>> for UninitSlice {
+    #[inline]
+    fn index_mut(&mut self, index: Range<usize>) -> &mut UninitSlice {
+        UninitSlice::from_uninit(&mut self.0[index])
+    }
+}
+
+impl IndexMut<RangeFrom<usize>> for UninitSlice {
+    #[inline]
+    fn index_mut(&mut self, index: RangeFrom<usize>) -> &mut UninitSlice {
+        let len = self.len();
+        &mut self[index.start..len]
+    }
+}
+
+impl IndexMut<RangeTo<usize>> for UninitSlice {
+    #[inline]
+    fn index_mut(&mut self, index: RangeTo<usize>) -> &mut UninitSlice {
+        &mut self[0..index.end]
+    }
+}
+
+impl IndexMut<RangeFull> for UninitSlice {
+    #[inline]
+    fn index_mut(&mut self, _index: RangeFull) -> &mut UninitSlice {
+        let len = self.len();
+        &mut self[0..len]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_byte() {
+        let mut buf = [MaybeUninit::new(0); 4];
+        let slice = UninitSlice::from_uninit(&mut buf);
+        slice.write_byte(1, 5);
+
+        assert_eq!(unsafe { buf[1].assume_init() }, 5);
+    }
+
+    #[test]
+    fn copy_from_slice() {
+        let mut buf = [MaybeUninit::new(0); 4];
+        let slice = UninitSlice::from_uninit(&mut buf);
+        slice[1..3].copy_from_slice(&[7, 8]);
+
+        assert_eq!(unsafe { buf[1].assume_init() }, 7);
+        assert_eq!(unsafe { buf[2].assume_init() }, 8);
+    }
+}